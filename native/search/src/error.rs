@@ -0,0 +1,45 @@
+use std::fmt;
+
+/// Crate-wide error type for the `search` crate.
+#[derive(Debug)]
+pub enum Error {
+    Tantivy(tantivy::TantivyError),
+    Json(serde_json::Error),
+    Io(std::io::Error),
+    NotOpen,
+    UnknownField(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Tantivy(err) => write!(f, "tantivy error: {}", err),
+            Error::Json(err) => write!(f, "json error: {}", err),
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::NotOpen => write!(f, "index has not been opened yet, call se_open_or_create first"),
+            Error::UnknownField(field) => write!(f, "unknown field: {}", field),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<tantivy::TantivyError> for Error {
+    fn from(err: tantivy::TantivyError) -> Error {
+        Error::Tantivy(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,60 @@
+//! Levenshtein-automaton term expansion for typo-tolerant search.
+
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder};
+use tantivy::schema::Field;
+use tantivy::{Searcher, Term};
+
+/// Picks the edit distance allowed for a token of this length: distance 0
+/// for very short tokens (so e.g. "a" doesn't fuzzy-match half the
+/// dictionary), 1 up to 7 chars, 2 beyond, capped at the caller-supplied
+/// `max_distance`.
+fn distance_for(token: &str, max_distance: u32) -> u32 {
+    let len = token.chars().count();
+    let scaled = if len <= 3 {
+        0
+    } else if len <= 7 {
+        1
+    } else {
+        2
+    };
+    scaled.min(max_distance)
+}
+
+/// Expands `token` into every term of `field`'s dictionary within the
+/// length-scaled edit distance, using tantivy's own Levenshtein-automaton
+/// machinery over the segment term dictionaries.
+pub fn expand_terms(
+    searcher: &Searcher,
+    field: Field,
+    token: &str,
+    max_distance: u32,
+) -> crate::Result<Vec<Term>> {
+    // The term dictionary is lowercased at index time (the field's
+    // `LowerCaser` tokenizer). Build the automaton from the same
+    // normalized form, or a mixed-case token either misses an exact
+    // (distance-0) match outright or burns part of its edit-distance
+    // budget on a case difference instead of an actual typo.
+    let token = token.to_lowercase();
+    let distance = distance_for(&token, max_distance);
+    let builder = LevenshteinAutomatonBuilder::new(distance, true);
+    let automaton = builder.build_dfa(&token);
+
+    let mut terms = vec![];
+    for segment_reader in searcher.segment_readers() {
+        let inverted_index = segment_reader.inverted_index(field)?;
+        let term_dict = inverted_index.terms();
+        let mut stream = term_dict.search(automaton.clone()).into_stream()?;
+        while let Some((term_bytes, _term_info)) = stream.next() {
+            if let Ok(text) = std::str::from_utf8(term_bytes) {
+                // Skip terms the automaton only reached via the Atlarge
+                // state; `eval` re-confirms an in-range match.
+                if !matches!(automaton.eval(text), Distance::AtLeast(_)) {
+                    terms.push(Term::from_field_text(field, text));
+                }
+            }
+        }
+    }
+    terms.sort();
+    terms.dedup();
+    Ok(terms)
+}
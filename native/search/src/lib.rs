@@ -0,0 +1,456 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use tantivy::collector::TopDocs;
+use tantivy::query::{BooleanQuery, Occur, Query, QueryParser, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, SchemaBuilder, INDEXED, STORED, STRING, TEXT};
+use tantivy::{Document, Index, IndexReader, IndexWriter, ReloadPolicy, Term};
+
+mod error;
+mod fuzzy;
+mod proximity;
+mod query_derivation;
+mod snippet;
+
+pub use error::{Error, Result};
+pub use snippet::{Snippet, SnippetGenerator};
+
+const WRITER_HEAP_BYTES: usize = 50_000_000;
+
+#[derive(Debug, Deserialize)]
+struct FieldSpec {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    stored: bool,
+    #[serde(default)]
+    fast: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct SchemaSpec {
+    fields: Vec<FieldSpec>,
+    #[serde(default)]
+    synonyms: BTreeMap<String, Vec<String>>,
+}
+
+struct IndexHandle {
+    index: Index,
+    schema: Schema,
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+    synonyms: BTreeMap<String, Vec<String>>,
+}
+
+static HANDLE: OnceCell<IndexHandle> = OnceCell::new();
+
+fn handle() -> Result<&'static IndexHandle> {
+    HANDLE.get().ok_or(Error::NotOpen)
+}
+
+fn field(schema: &Schema, name: &str) -> Result<Field> {
+    schema
+        .get_field(name)
+        .ok_or_else(|| Error::UnknownField(name.to_string()))
+}
+
+fn build_schema(spec: &SchemaSpec) -> Schema {
+    let mut builder: SchemaBuilder = Schema::builder();
+    for f in &spec.fields {
+        match f.kind.as_str() {
+            "text" => {
+                if f.stored {
+                    builder.add_text_field(&f.name, TEXT | STORED);
+                } else {
+                    builder.add_text_field(&f.name, TEXT);
+                }
+            }
+            "string" => {
+                if f.stored {
+                    builder.add_text_field(&f.name, STRING | STORED);
+                } else {
+                    builder.add_text_field(&f.name, STRING);
+                }
+            }
+            "u64" => {
+                if f.fast {
+                    builder.add_u64_field(&f.name, INDEXED | STORED | tantivy::schema::FAST);
+                } else if f.stored {
+                    builder.add_u64_field(&f.name, INDEXED | STORED);
+                } else {
+                    builder.add_u64_field(&f.name, INDEXED);
+                }
+            }
+            "i64" => {
+                if f.stored {
+                    builder.add_i64_field(&f.name, INDEXED | STORED);
+                } else {
+                    builder.add_i64_field(&f.name, INDEXED);
+                }
+            }
+            other => {
+                // Unknown field kinds default to a stored text field so an
+                // open() call never hard-fails on a forward-compatible schema.
+                let _ = other;
+                builder.add_text_field(&f.name, TEXT | STORED);
+            }
+        }
+    }
+    builder.build()
+}
+
+/// Opens the index at `path`, creating it (and its schema) on first use.
+///
+/// `schema` is the JSON schema description shipped from the Dart side: a
+/// list of fields plus an optional `synonyms` table used by
+/// `search_derived`.
+pub fn open(path: &str, schema: &str) -> Result<()> {
+    let spec: SchemaSpec = serde_json::from_str(schema)?;
+    let built_schema = build_schema(&spec);
+
+    let dir = tantivy::directory::MmapDirectory::open(path)?;
+    let index = Index::open_or_create(dir, built_schema.clone())?;
+    let writer = index.writer(WRITER_HEAP_BYTES)?;
+    let reader = index
+        .reader_builder()
+        .reload_policy(ReloadPolicy::OnCommit)
+        .try_into()?;
+
+    let _ = HANDLE.set(IndexHandle {
+        index,
+        schema: built_schema,
+        reader,
+        writer: Mutex::new(writer),
+        synonyms: spec.synonyms,
+    });
+    Ok(())
+}
+
+/// Returns whether the index has already been opened.
+pub async fn exists() -> Result<bool> {
+    Ok(HANDLE.get().is_some())
+}
+
+/// Adds a single JSON-encoded document to the index.
+pub async fn index(doc: &str) -> Result<()> {
+    let h = handle()?;
+    let document: Document = h.schema.parse_document(doc)?;
+    let writer = h.writer.lock().unwrap();
+    writer.add_document(document);
+    Ok(())
+}
+
+fn query_fields(h: &'static IndexHandle, fields: &[String]) -> Result<Vec<Field>> {
+    fields.iter().map(|name| field(&h.schema, name)).collect()
+}
+
+fn hit_to_json(h: &'static IndexHandle, score: f32, doc: &Document) -> serde_json::Value {
+    let mut value = h.schema.to_json(doc);
+    if let serde_json::Value::Object(ref mut map) = value {
+        map.insert("_score".to_string(), serde_json::json!(score));
+    }
+    value
+}
+
+/// Plain relevance search, the original FFI entry point's backing function.
+pub async fn search(
+    query: &str,
+    fields: Vec<String>,
+    page_start: usize,
+    page_size: usize,
+) -> Result<String> {
+    let h = handle()?;
+    let searcher = h.reader.searcher();
+    let search_fields = query_fields(h, &fields)?;
+    let parser = QueryParser::for_index(&h.index, search_fields);
+    let parsed = parser.parse_query(query)?;
+
+    let top_docs = searcher.search(&parsed, &TopDocs::with_limit(page_start + page_size))?;
+    let mut results = vec![];
+    for (score, address) in top_docs.into_iter().skip(page_start) {
+        let doc = searcher.doc(address)?;
+        results.push(hit_to_json(h, score, &doc));
+    }
+    Ok(serde_json::to_string(&results)?)
+}
+
+/// Relevance search that additionally attaches a highlighted snippet per
+/// requested field to each hit, using `SnippetGenerator`.
+pub async fn search_with_snippets(
+    query: &str,
+    fields: Vec<String>,
+    snippet_fields: Vec<String>,
+    max_num_chars: usize,
+    highlight_prefix: &str,
+    highlight_postfix: &str,
+    page_start: usize,
+    page_size: usize,
+) -> Result<String> {
+    let h = handle()?;
+    let searcher = h.reader.searcher();
+    let search_fields = query_fields(h, &fields)?;
+    let parser = QueryParser::for_index(&h.index, search_fields);
+    let parsed = parser.parse_query(query)?;
+
+    let mut generators = BTreeMap::new();
+    for name in &snippet_fields {
+        let f = field(&h.schema, name)?;
+        let mut generator = SnippetGenerator::create(&searcher, &*parsed, f)?;
+        generator.set_max_num_chars(max_num_chars);
+        generator.set_highlight_tags(highlight_prefix.to_string(), highlight_postfix.to_string());
+        generators.insert(name.clone(), generator);
+    }
+
+    let top_docs = searcher.search(&parsed, &TopDocs::with_limit(page_start + page_size))?;
+    let mut results = vec![];
+    for (score, address) in top_docs.into_iter().skip(page_start) {
+        let doc = searcher.doc(address)?;
+        let mut value = hit_to_json(h, score, &doc);
+        if let serde_json::Value::Object(ref mut map) = value {
+            let mut snippets = serde_json::Map::new();
+            for (name, generator) in &generators {
+                let snippet = generator.snippet_from_doc(&doc);
+                snippets.insert(name.clone(), serde_json::json!(snippet.to_html()));
+            }
+            map.insert("_snippets".to_string(), serde_json::Value::Object(snippets));
+        }
+        results.push(value);
+    }
+    Ok(serde_json::to_string(&results)?)
+}
+
+/// Typo-tolerant search: every query token is expanded into the set of
+/// index terms within an edit distance scaled by the token's length, the
+/// expansions are OR-combined per token (keeping `search`'s cross-field
+/// behavior), and the expanded terms are fed into the snippet highlighter
+/// so fuzzily-matched words still get marked.
+pub async fn search_fuzzy(
+    query: &str,
+    fields: Vec<String>,
+    max_distance: u32,
+    page_start: usize,
+    page_size: usize,
+) -> Result<String> {
+    let h = handle()?;
+    let searcher = h.reader.searcher();
+    let search_fields = query_fields(h, &fields)?;
+
+    let tokens: Vec<&str> = query.split_whitespace().collect();
+    let mut per_token_clauses = vec![];
+    let mut expanded_by_field: BTreeMap<Field, Vec<Term>> = BTreeMap::new();
+
+    for token in &tokens {
+        let mut token_clauses = vec![];
+        for &f in &search_fields {
+            let expanded = fuzzy::expand_terms(&searcher, f, token, max_distance)?;
+            for term in &expanded {
+                token_clauses.push((
+                    Occur::Should,
+                    Box::new(TermQuery::new(term.clone(), IndexRecordOption::WithFreqs)) as Box<dyn Query>,
+                ));
+            }
+            expanded_by_field.entry(f).or_default().extend(expanded);
+        }
+        per_token_clauses.push((Occur::Must, Box::new(BooleanQuery::from(token_clauses)) as Box<dyn Query>));
+    }
+    let parsed: Box<dyn Query> = Box::new(BooleanQuery::from(per_token_clauses));
+
+    let mut generators = BTreeMap::new();
+    for &f in &search_fields {
+        let extra = expanded_by_field.remove(&f).unwrap_or_default();
+        generators.insert(
+            f,
+            SnippetGenerator::create_with_additional_terms(&searcher, extra, f)?,
+        );
+    }
+
+    let top_docs = searcher.search(&*parsed, &TopDocs::with_limit(page_start + page_size))?;
+    let mut results = vec![];
+    for (score, address) in top_docs.into_iter().skip(page_start) {
+        let doc = searcher.doc(address)?;
+        let mut value = hit_to_json(h, score, &doc);
+        if let serde_json::Value::Object(ref mut map) = value {
+            let mut snippets = serde_json::Map::new();
+            for (f, generator) in &generators {
+                let name = h.schema.get_field_name(*f);
+                snippets.insert(
+                    name.to_string(),
+                    serde_json::json!(generator.snippet_from_doc(&doc).to_html()),
+                );
+            }
+            map.insert("_snippets".to_string(), serde_json::Value::Object(snippets));
+        }
+        results.push(value);
+    }
+    Ok(serde_json::to_string(&results)?)
+}
+
+/// Relevance search with a secondary proximity re-ranking pass: the first
+/// page of BM25 candidates is re-ordered so documents whose query terms
+/// appear close together (and in order) rank higher. See the `proximity`
+/// module for the per-document cost computation.
+pub async fn search_proximity(
+    query: &str,
+    fields: Vec<String>,
+    proximity_weight: f32,
+    page_start: usize,
+    page_size: usize,
+) -> Result<String> {
+    let h = handle()?;
+    let searcher = h.reader.searcher();
+    let search_fields = query_fields(h, &fields)?;
+    let parser = QueryParser::for_index(&h.index, search_fields.clone());
+    let parsed = parser.parse_query(query)?;
+
+    let query_terms: Vec<String> = query.split_whitespace().map(|t| t.to_lowercase()).collect();
+
+    // Only the first page of candidates gets the (more expensive) proximity
+    // treatment, keeping the number of postings-list touches bounded.
+    let top_docs = searcher.search(&parsed, &TopDocs::with_limit(page_start + page_size))?;
+    let mut scored = vec![];
+    for (bm25_score, address) in top_docs {
+        let cost = proximity::proximity_cost(&searcher, &search_fields, &query_terms, address)?;
+        let blended = bm25_score - proximity_weight * cost as f32;
+        scored.push((blended, bm25_score, address));
+    }
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut results = vec![];
+    for (_, bm25_score, address) in scored.into_iter().skip(page_start) {
+        let doc = searcher.doc(address)?;
+        results.push(hit_to_json(h, bm25_score, &doc));
+    }
+    Ok(serde_json::to_string(&results)?)
+}
+
+/// Search using the query-term derivation graph (prefix / split / merged /
+/// synonym alternatives) instead of a single literal parse. See the
+/// `query_derivation` module.
+pub async fn search_derived(
+    query: &str,
+    fields: Vec<String>,
+    enable_derivation: bool,
+    page_start: usize,
+    page_size: usize,
+) -> Result<String> {
+    let h = handle()?;
+    let search_fields = query_fields(h, &fields)?;
+
+    if !enable_derivation {
+        return search(query, fields, page_start, page_size).await;
+    }
+
+    let searcher = h.reader.searcher();
+    let in_dictionary = |candidate: &str| -> bool {
+        search_fields.iter().any(|&f| {
+            let term = Term::from_field_text(f, candidate);
+            searcher.doc_freq(&term).unwrap_or(0) > 0
+        })
+    };
+    let graph = query_derivation::QueryDerivationGraph::build(query, in_dictionary, &h.synonyms);
+
+    let per_field_clauses: Vec<(Occur, Box<dyn Query>)> = search_fields
+        .iter()
+        .map(|&f| (Occur::Should, graph.compile(f)))
+        .collect();
+    let parsed: Box<dyn Query> = Box::new(BooleanQuery::from(per_field_clauses));
+
+    let mut generators = BTreeMap::new();
+    let derived_terms: Vec<Term> = graph
+        .all_term_texts()
+        .into_iter()
+        .flat_map(|text| search_fields.iter().map(move |&f| Term::from_field_text(f, &text)))
+        .collect();
+    for &f in &search_fields {
+        let terms_for_field: Vec<Term> = derived_terms
+            .iter()
+            .filter(|t| t.field() == f)
+            .cloned()
+            .collect();
+        generators.insert(
+            f,
+            SnippetGenerator::create_with_additional_terms(&searcher, terms_for_field, f)?,
+        );
+    }
+
+    let top_docs = searcher.search(&*parsed, &TopDocs::with_limit(page_start + page_size))?;
+    let mut results = vec![];
+    for (score, address) in top_docs.into_iter().skip(page_start) {
+        let doc = searcher.doc(address)?;
+        let mut value = hit_to_json(h, score, &doc);
+        if let serde_json::Value::Object(ref mut map) = value {
+            let mut snippets = serde_json::Map::new();
+            for (f, generator) in &generators {
+                let name = h.schema.get_field_name(*f);
+                snippets.insert(
+                    name.to_string(),
+                    serde_json::json!(generator.snippet_from_doc(&doc).to_html()),
+                );
+            }
+            map.insert("_snippets".to_string(), serde_json::Value::Object(snippets));
+        }
+        results.push(value);
+    }
+    Ok(serde_json::to_string(&results)?)
+}
+
+fn delete_by_term(term: Term) -> Result<()> {
+    let h = handle()?;
+    let writer = h.writer.lock().unwrap();
+    writer.delete_term(term);
+    Ok(())
+}
+
+pub async fn delete_by_str(field_name: &str, value: &str) -> Result<()> {
+    let h = handle()?;
+    let f = field(&h.schema, field_name)?;
+    delete_by_term(Term::from_field_text(f, value))
+}
+
+pub async fn delete_by_u64(field_name: &str, value: u64) -> Result<()> {
+    let h = handle()?;
+    let f = field(&h.schema, field_name)?;
+    delete_by_term(Term::from_field_u64(f, value))
+}
+
+pub async fn update_by_str(field_name: &str, value: &str, doc: &str) -> Result<()> {
+    let h = handle()?;
+    let f = field(&h.schema, field_name)?;
+    delete_by_term(Term::from_field_text(f, value))?;
+    index(doc).await
+}
+
+pub async fn update_by_u64(field_name: &str, value: u64, doc: &str) -> Result<()> {
+    let h = handle()?;
+    let f = field(&h.schema, field_name)?;
+    delete_by_term(Term::from_field_u64(f, value))?;
+    index(doc).await
+}
+
+fn get_by_term(term: Term) -> Result<String> {
+    let h = handle()?;
+    let searcher = h.reader.searcher();
+    let query = TermQuery::new(term, IndexRecordOption::Basic);
+    let top_docs = searcher.search(&query, &TopDocs::with_limit(1))?;
+    let mut results = vec![];
+    for (score, address) in top_docs {
+        let doc = searcher.doc(address)?;
+        results.push(hit_to_json(h, score, &doc));
+    }
+    Ok(serde_json::to_string(&results)?)
+}
+
+pub async fn get_by_term_u64(field_name: &str, value: u64) -> Result<String> {
+    let h = handle()?;
+    let f = field(&h.schema, field_name)?;
+    get_by_term(Term::from_field_u64(f, value))
+}
+
+pub async fn get_by_term_i64(field_name: &str, value: i64) -> Result<String> {
+    let h = handle()?;
+    let f = field(&h.schema, field_name)?;
+    get_by_term(Term::from_field_i64(f, value))
+}
@@ -0,0 +1,277 @@
+//! Query-preprocessing subsystem: turns a raw query string into a graph of
+//! alternative interpretations (prefix, split/merged compounds, synonyms)
+//! before it reaches tantivy, instead of a single literal parse.
+//!
+//! Registered from the crate root via `mod query_derivation;`, alongside
+//! `mod snippet;`.
+
+use std::collections::BTreeMap;
+
+use tantivy::query::{BooleanQuery, Occur, Query, RegexQuery, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::Term;
+
+/// One way a single query token can be satisfied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Derivation {
+    /// The token itself, matched verbatim.
+    Literal(String),
+    /// The last token of the query, matched as a prefix (as-you-type search).
+    Prefix(String),
+    /// Two adjacent tokens merged into one (e.g. "note" + "book" -> "notebook").
+    Merged(String),
+    /// A single token split into two (e.g. "notebook" -> "note" + "book").
+    Split(Vec<String>),
+    /// A caller-supplied synonym for the token.
+    Synonym(String),
+}
+
+/// One position in the query, along with every way it can be satisfied.
+#[derive(Debug, Clone)]
+pub struct DerivationNode {
+    alternatives: Vec<Derivation>,
+}
+
+impl DerivationNode {
+    fn literal(token: &str) -> DerivationNode {
+        DerivationNode {
+            alternatives: vec![Derivation::Literal(token.to_string())],
+        }
+    }
+
+    /// Adds another way this position can be matched.
+    pub fn push(&mut self, derivation: Derivation) {
+        if !self.alternatives.contains(&derivation) {
+            self.alternatives.push(derivation);
+        }
+    }
+}
+
+/// An ordered DAG of query-term derivations.
+///
+/// Each position in the original query is represented by a [`DerivationNode`]
+/// listing every alternative interpretation (literal, prefix, split/merged
+/// compounds, synonyms). `compile` turns this into a single tantivy query:
+/// alternatives at a position are OR-ed together, and positions are AND-ed.
+#[derive(Debug, Clone, Default)]
+pub struct QueryDerivationGraph {
+    nodes: Vec<DerivationNode>,
+}
+
+impl QueryDerivationGraph {
+    /// Builds the derivation graph for a raw, whitespace-tokenized query.
+    ///
+    /// `in_dictionary` reports whether a candidate term exists in the index,
+    /// used to decide whether a split/merge candidate is worth keeping.
+    /// `synonyms` is the caller-supplied synonym table loaded from the
+    /// schema JSON passed to `search::open`.
+    pub fn build(
+        query: &str,
+        in_dictionary: impl Fn(&str) -> bool,
+        synonyms: &BTreeMap<String, Vec<String>>,
+    ) -> QueryDerivationGraph {
+        // `search`'s plain path goes through `QueryParser`, which runs the
+        // field's tokenizer (including `LowerCaser` for `TEXT` fields)
+        // before matching. Derivation terms are built by hand, so they need
+        // the same normalization up front, or capitalized input would
+        // silently fail to match the (lowercased) index dictionary.
+        let lowered = query.to_lowercase();
+        let tokens: Vec<&str> = lowered.split_whitespace().collect();
+        let mut nodes: Vec<DerivationNode> = tokens
+            .iter()
+            .map(|token| DerivationNode::literal(token))
+            .collect();
+
+        for (i, token) in tokens.iter().enumerate() {
+            if let Some(alts) = synonyms.get(&token.to_lowercase()) {
+                for alt in alts {
+                    nodes[i].push(Derivation::Synonym(alt.clone()));
+                }
+            }
+        }
+
+        // Merge adjacent tokens into a single compound candidate, e.g.
+        // "note book" <-> "notebook". The merged term is pushed onto BOTH
+        // positions it replaces: a document containing "notebook" must
+        // satisfy both the `i` and `i+1` Musts in `compile`, and since
+        // they now share the same alternative, a single match on the
+        // compound term satisfies both, rather than leaving `i+1` stuck
+        // requiring the literal "book".
+        for i in 0..tokens.len().saturating_sub(1) {
+            let merged = format!("{}{}", tokens[i], tokens[i + 1]);
+            if in_dictionary(&merged) {
+                nodes[i].push(Derivation::Merged(merged.clone()));
+                nodes[i + 1].push(Derivation::Merged(merged));
+            }
+        }
+
+        // Split a single compound token against the index dictionary, e.g.
+        // "notebook" -> "note" + "book".
+        for (i, token) in tokens.iter().enumerate() {
+            if let Some((left, right)) = split_against_dictionary(token, &in_dictionary) {
+                nodes[i].push(Derivation::Split(vec![left, right]));
+            }
+        }
+
+        // The last token is additionally treated as a prefix, for
+        // as-you-type search.
+        if let Some(last) = nodes.last_mut() {
+            if let Some(&last_token) = tokens.last() {
+                last.push(Derivation::Prefix(last_token.to_string()));
+            }
+        }
+
+        QueryDerivationGraph { nodes }
+    }
+
+    /// Compiles the graph into a tantivy query: OR across derivations at
+    /// each position, AND across positions.
+    pub fn compile(&self, field: Field) -> Box<dyn Query> {
+        let position_queries: Vec<(Occur, Box<dyn Query>)> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let alt_queries: Vec<(Occur, Box<dyn Query>)> = node
+                    .alternatives
+                    .iter()
+                    .map(|derivation| (Occur::Should, derivation_to_query(derivation, field)))
+                    .collect();
+                (
+                    Occur::Must,
+                    Box::new(BooleanQuery::from(alt_queries)) as Box<dyn Query>,
+                )
+            })
+            .collect();
+        Box::new(BooleanQuery::from(position_queries))
+    }
+
+    /// Every term text of every alternative at every position, flattened.
+    /// Passed to the snippet highlighter so matches on synonyms or split
+    /// forms are still marked, not just the literal query terms. A prefix
+    /// alternative contributes only its prefix text, not the index terms
+    /// that the prefix matches.
+    pub fn all_term_texts(&self) -> Vec<String> {
+        self.nodes
+            .iter()
+            .flat_map(|node| node.alternatives.iter())
+            .flat_map(|derivation| match derivation {
+                Derivation::Literal(t) | Derivation::Prefix(t) | Derivation::Merged(t) => {
+                    vec![t.clone()]
+                }
+                Derivation::Synonym(t) => vec![t.clone()],
+                Derivation::Split(parts) => parts.clone(),
+            })
+            .collect()
+    }
+}
+
+fn derivation_to_query(derivation: &Derivation, field: Field) -> Box<dyn Query> {
+    match derivation {
+        Derivation::Literal(text) | Derivation::Merged(text) | Derivation::Synonym(text) => {
+            Box::new(term_query(field, text))
+        }
+        Derivation::Prefix(text) => {
+            // `text` is user input and may contain regex metacharacters
+            // (`.`, `*`, `(`, ...); escape it so the pattern means
+            // "starts with this literal text", not an arbitrary regex.
+            let pattern = format!("{}.*", regex::escape(text));
+            match RegexQuery::from_pattern(&pattern, field) {
+                Ok(query) => Box::new(query),
+                Err(_) => Box::new(term_query(field, text)),
+            }
+        }
+        Derivation::Split(parts) => {
+            let subqueries: Vec<(Occur, Box<dyn Query>)> = parts
+                .iter()
+                .map(|part| (Occur::Must, Box::new(term_query(field, part)) as Box<dyn Query>))
+                .collect();
+            Box::new(BooleanQuery::from(subqueries))
+        }
+    }
+}
+
+fn term_query(field: Field, text: &str) -> TermQuery {
+    TermQuery::new(
+        Term::from_field_text(field, text),
+        IndexRecordOption::WithFreqsAndPositions,
+    )
+}
+
+/// Tries every split point of `token` and returns the first `(left, right)`
+/// pair where both halves are real dictionary terms.
+fn split_against_dictionary(
+    token: &str,
+    in_dictionary: impl Fn(&str) -> bool,
+) -> Option<(String, String)> {
+    if token.len() < 2 {
+        return None;
+    }
+    for split_at in 1..token.len() {
+        if !token.is_char_boundary(split_at) {
+            continue;
+        }
+        let (left, right) = token.split_at(split_at);
+        if in_dictionary(left) && in_dictionary(right) {
+            return Some((left.to_string(), right.to_string()));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_is_added_to_both_positions_it_replaces() {
+        let synonyms = BTreeMap::new();
+        let dictionary = ["notebook"];
+        let graph = QueryDerivationGraph::build(
+            "note book",
+            |candidate| dictionary.contains(&candidate),
+            &synonyms,
+        );
+
+        assert_eq!(graph.nodes.len(), 2);
+        assert!(graph.nodes[0].alternatives.contains(&Derivation::Merged("notebook".to_string())));
+        assert!(graph.nodes[1].alternatives.contains(&Derivation::Merged("notebook".to_string())));
+        // Still keeps the literal reading of each token as an alternative.
+        assert!(graph.nodes[0].alternatives.contains(&Derivation::Literal("note".to_string())));
+        assert!(graph.nodes[1].alternatives.contains(&Derivation::Literal("book".to_string())));
+    }
+
+    #[test]
+    fn split_is_added_only_when_both_halves_are_in_dictionary() {
+        let synonyms = BTreeMap::new();
+        let dictionary = ["note", "book"];
+        let graph = QueryDerivationGraph::build(
+            "notebook",
+            |candidate| dictionary.contains(&candidate),
+            &synonyms,
+        );
+
+        assert_eq!(graph.nodes.len(), 1);
+        assert!(graph.nodes[0].alternatives.contains(&Derivation::Split(vec![
+            "note".to_string(),
+            "book".to_string(),
+        ])));
+    }
+
+    #[test]
+    fn split_is_not_added_when_a_half_is_missing_from_dictionary() {
+        let synonyms = BTreeMap::new();
+        let dictionary = ["note"];
+        let graph = QueryDerivationGraph::build(
+            "notebook",
+            |candidate| dictionary.contains(&candidate),
+            &synonyms,
+        );
+
+        assert_eq!(graph.nodes.len(), 1);
+        assert!(!graph
+            .nodes[0]
+            .alternatives
+            .iter()
+            .any(|d| matches!(d, Derivation::Split(_))));
+    }
+}
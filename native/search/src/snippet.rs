@@ -75,6 +75,8 @@ impl FragmentCandidate {
 pub struct Snippet {
     fragments: String,
     highlighted: Vec<HighlightSection>,
+    highlight_prefix: String,
+    highlight_postfix: String,
 }
 
 const HIGHLIGHTEN_PREFIX: &str = "<b>";
@@ -86,6 +88,8 @@ impl Snippet {
         Snippet {
             fragments: String::new(),
             highlighted: Vec::new(),
+            highlight_prefix: HIGHLIGHTEN_PREFIX.to_string(),
+            highlight_postfix: HIGHLIGHTEN_POSTFIX.to_string(),
         }
     }
 
@@ -96,9 +100,9 @@ impl Snippet {
 
         for item in self.highlighted.iter() {
             html.push_str(&encode_minimal(&self.fragments[start_from..item.start]));
-            html.push_str(HIGHLIGHTEN_PREFIX);
+            html.push_str(&self.highlight_prefix);
             html.push_str(&encode_minimal(&self.fragments[item.start..item.stop]));
-            html.push_str(HIGHLIGHTEN_POSTFIX);
+            html.push_str(&self.highlight_postfix);
             start_from = item.stop;
         }
         html.push_str(&encode_minimal(
@@ -114,9 +118,9 @@ impl Snippet {
 
         for item in self.highlighted.iter() {
             html.push_str(&self.fragments[start_from..item.start]);
-            html.push_str(HIGHLIGHTEN_PREFIX);
+            html.push_str(&self.highlight_prefix);
             html.push_str(&self.fragments[item.start..item.stop]);
-            html.push_str(HIGHLIGHTEN_POSTFIX);
+            html.push_str(&self.highlight_postfix);
             start_from = item.stop;
         }
 
@@ -130,6 +134,16 @@ impl Snippet {
         &self.fragments
     }
 
+    /// Returns the delimiter wrapped before a highlighted match.
+    pub fn highlight_prefix(&self) -> &str {
+        &self.highlight_prefix
+    }
+
+    /// Returns the delimiter wrapped after a highlighted match.
+    pub fn highlight_postfix(&self) -> &str {
+        &self.highlight_postfix
+    }
+
     /// Returns a list of higlighted positions from the `Snippet`.
     pub fn highlighted(&self) -> &[HighlightSection] {
         &self.highlighted
@@ -169,6 +183,9 @@ fn search_fragments<'a>(
     while let Some(next) = token_stream.next() {
         if (next.offset_to - fragment.start_offset) > max_num_chars {
             if fragment.score > 0.0 {
+                fragment.num_chars = text[fragment.start_offset..fragment.stop_offset]
+                    .chars()
+                    .count();
                 fragments.push(fragment)
             };
             fragment = FragmentCandidate::new(next.offset_from);
@@ -176,51 +193,103 @@ fn search_fragments<'a>(
         fragment.try_add_token(next, &terms);
     }
     if fragment.score > 0.0 {
+        fragment.num_chars = text[fragment.start_offset..fragment.stop_offset]
+            .chars()
+            .count();
         fragments.push(fragment)
     }
 
     fragments
 }
 
+/// Separator inserted between concatenated fragments in a multi-fragment `Snippet`.
+const FRAGMENT_SEPARATOR: &str = " … ";
+
+/// How many times the per-fragment `max_num_chars` window the *combined*
+/// snippet is allowed to span. Each individual fragment is already capped
+/// at `max_num_chars` by `search_fragments`; if the total budget were the
+/// same value, the single highest-scoring fragment could consume it
+/// entirely and a second fragment could never be admitted. Multiplying it
+/// out is what actually lets more than one match window surface.
+const TOTAL_BUDGET_MULTIPLE: usize = 3;
+
 /// Returns a Snippet
 ///
 /// Takes a vector of `FragmentCandidate`s and the text.
-/// Figures out the best fragment from it and creates a snippet.
-fn select_best_fragment_combination(fragments: &[FragmentCandidate], text: &str) -> Snippet {
-    let best_fragment_opt = fragments.iter().max_by(|left, right| {
-        let cmp_score = left
+///
+/// Greedily selects as many non-overlapping fragments as fit within a
+/// total budget of `max_num_chars * TOTAL_BUDGET_MULTIPLE`, highest-scoring
+/// first, so a long document can surface more than one match window
+/// instead of just the single best one. The selected fragments are then
+/// re-ordered by their position in `text` and concatenated, separated by
+/// `FRAGMENT_SEPARATOR`, with their `HighlightSection`s remapped into the
+/// concatenated string.
+fn select_best_fragment_combination(
+    fragments: &[FragmentCandidate],
+    text: &str,
+    max_num_chars: usize,
+    highlight_prefix: &str,
+    highlight_postfix: &str,
+) -> Snippet {
+    let mut by_score: Vec<&FragmentCandidate> = fragments.iter().collect();
+    by_score.sort_by(|left, right| {
+        right
             .score
-            .partial_cmp(&right.score)
-            .unwrap_or(Ordering::Equal);
-        if cmp_score == Ordering::Equal {
-            (right.start_offset, right.stop_offset).cmp(&(left.start_offset, left.stop_offset))
-        } else {
-            cmp_score
-        }
+            .partial_cmp(&left.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| left.start_offset.cmp(&right.start_offset))
     });
-    if let Some(fragment) = best_fragment_opt {
-        let fragment_text = &text[fragment.start_offset..fragment.stop_offset];
-        let highlighted = fragment
-            .highlighted
-            .iter()
-            .map(|item| {
-                HighlightSection::new(
-                    item.start - fragment.start_offset,
-                    item.stop - fragment.start_offset,
-                )
-            })
-            .collect();
-        Snippet {
-            fragments: fragment_text.to_string(),
-            highlighted,
+
+    let mut selected: Vec<&FragmentCandidate> = vec![];
+    let mut remaining_chars = max_num_chars.saturating_mul(TOTAL_BUDGET_MULTIPLE);
+    for candidate in by_score {
+        if candidate.num_chars > remaining_chars {
+            continue;
         }
-    } else {
+        let overlaps = selected.iter().any(|picked| {
+            candidate.start_offset < picked.stop_offset && picked.start_offset < candidate.stop_offset
+        });
+        if overlaps {
+            continue;
+        }
+        remaining_chars -= candidate.num_chars;
+        selected.push(candidate);
+    }
+
+    if selected.is_empty() {
         // when there no fragments to chose from,
         // for now create a empty snippet
-        Snippet {
+        return Snippet {
             fragments: String::new(),
             highlighted: vec![],
+            highlight_prefix: highlight_prefix.to_string(),
+            highlight_postfix: highlight_postfix.to_string(),
+        };
+    }
+
+    selected.sort_by_key(|fragment| fragment.start_offset);
+
+    let mut fragments_text = String::new();
+    let mut highlighted = vec![];
+    for (i, fragment) in selected.iter().enumerate() {
+        if i > 0 {
+            fragments_text.push_str(FRAGMENT_SEPARATOR);
         }
+        let base_offset = fragments_text.len();
+        fragments_text.push_str(&text[fragment.start_offset..fragment.stop_offset]);
+        highlighted.extend(fragment.highlighted.iter().map(|item| {
+            HighlightSection::new(
+                base_offset + item.start - fragment.start_offset,
+                base_offset + item.stop - fragment.start_offset,
+            )
+        }));
+    }
+
+    Snippet {
+        fragments: fragments_text,
+        highlighted,
+        highlight_prefix: highlight_prefix.to_string(),
+        highlight_postfix: highlight_postfix.to_string(),
     }
 }
 
@@ -270,6 +339,8 @@ pub struct SnippetGenerator {
     tokenizer: TextAnalyzer,
     field: Field,
     max_num_chars: usize,
+    highlight_prefix: String,
+    highlight_postfix: String,
 }
 
 impl SnippetGenerator {
@@ -281,6 +352,21 @@ impl SnippetGenerator {
     ) -> crate::Result<SnippetGenerator> {
         let mut terms = BTreeSet::new();
         query.query_terms(&mut terms);
+        Self::create_with_additional_terms(searcher, terms, field)
+    }
+
+    /// Creates a new snippet generator from an explicit term set rather
+    /// than deriving it from a query's literal terms.
+    ///
+    /// This is used by fuzzy/typo-tolerant search: the literal query is
+    /// expanded into a broader set of index terms before it reaches
+    /// tantivy, and that same expanded set must be passed in here so the
+    /// expanded (not just the literal) matches get highlighted.
+    pub fn create_with_additional_terms(
+        searcher: &Searcher,
+        terms: impl IntoIterator<Item = tantivy::Term>,
+        field: Field,
+    ) -> crate::Result<SnippetGenerator> {
         let mut terms_text: BTreeMap<String, Score> = Default::default();
         for term in terms {
             if term.field() != field {
@@ -298,9 +384,20 @@ impl SnippetGenerator {
             tokenizer,
             field,
             max_num_chars: DEFAULT_MAX_NUM_CHARS,
+            highlight_prefix: HIGHLIGHTEN_PREFIX.to_string(),
+            highlight_postfix: HIGHLIGHTEN_POSTFIX.to_string(),
         })
     }
 
+    /// Sets the delimiters wrapped around highlighted matches in produced `Snippet`s.
+    ///
+    /// Defaults to `<b>`/`</b>`. Callers can use this to request markdown
+    /// (`**`/`**`), a different tag (`<mark>`/`</mark>`), ANSI escapes, etc.
+    pub fn set_highlight_tags(&mut self, prefix: impl Into<String>, postfix: impl Into<String>) {
+        self.highlight_prefix = prefix.into();
+        self.highlight_postfix = postfix.into();
+    }
+
     /// Sets a maximum number of chars.
     pub fn set_max_num_chars(&mut self, max_num_chars: usize) {
         self.max_num_chars = max_num_chars;
@@ -328,6 +425,72 @@ impl SnippetGenerator {
     pub fn snippet(&self, text: &str) -> Snippet {
         let fragment_candidates =
             search_fragments(&self.tokenizer, &text, &self.terms_text, self.max_num_chars);
-        select_best_fragment_combination(&fragment_candidates[..], &text)
+        select_best_fragment_combination(
+            &fragment_candidates[..],
+            &text,
+            self.max_num_chars,
+            &self.highlight_prefix,
+            &self.highlight_postfix,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(start: usize, stop: usize, score: Score, highlighted: Vec<(usize, usize)>) -> FragmentCandidate {
+        FragmentCandidate {
+            score,
+            start_offset: start,
+            stop_offset: stop,
+            num_chars: stop - start,
+            highlighted: highlighted
+                .into_iter()
+                .map(|(from, to)| HighlightSection::new(from, to))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn select_best_fragment_combination_picks_multiple_non_overlapping_fragments() {
+        let text = "aaaa one bbbb cccc two dddd";
+        //          0    5   9    14   19  23
+        let fragments = vec![
+            candidate(5, 8, 2.0, vec![(5, 8)]),   // "one"
+            candidate(19, 22, 1.0, vec![(19, 22)]), // "two"
+        ];
+
+        let snippet = select_best_fragment_combination(&fragments, text, 150, "<b>", "</b>");
+
+        assert!(snippet.fragments().contains("one"));
+        assert!(snippet.fragments().contains("two"));
+        assert_eq!(snippet.highlighted().len(), 2);
+        for section in snippet.highlighted() {
+            let (start, stop) = section.bounds();
+            // Every highlighted range must be a valid, in-bounds substring
+            // of the concatenated snippet text.
+            assert!(snippet.fragments().get(start..stop).is_some());
+        }
+        // Fragments must come out ordered by their position in the source
+        // text, "one" before "two".
+        let one_pos = snippet.fragments().find("one").unwrap();
+        let two_pos = snippet.fragments().find("two").unwrap();
+        assert!(one_pos < two_pos);
+    }
+
+    #[test]
+    fn select_best_fragment_combination_rejects_overlapping_fragments() {
+        let text = "one two three";
+        let fragments = vec![
+            candidate(0, 7, 2.0, vec![(0, 3)]),
+            candidate(4, 13, 1.0, vec![(4, 7)]),
+        ];
+
+        let snippet = select_best_fragment_combination(&fragments, text, 150, "<b>", "</b>");
+
+        // The two candidates overlap ([0,7) and [4,13)); only the
+        // higher-scoring one should be kept.
+        assert_eq!(snippet.fragments(), "one two");
     }
 }
\ No newline at end of file
@@ -96,6 +96,150 @@ pub extern "C" fn se_search(
     1
 }
 
+#[no_mangle]
+pub extern "C" fn se_search_with_snippets(
+    port: i64,
+    query: *const c_char,
+    fields: *const c_char,
+    snippet_fields: *const c_char,
+    max_num_chars: u32,
+    highlight_prefix: *const c_char,
+    highlight_postfix: *const c_char,
+    page_start: u32,
+    page_size: u32,
+) -> i32 {
+    let rt = runtime!();
+    let query = cstr!(query);
+    let fields = cstr!(fields);
+    let fields = match serde_json::from_str::<Vec<String>>(&fields) {
+        Ok(v) => v,
+        Err(err) => {
+            update_last_error(err);
+            return 0;
+        }
+    };
+    let snippet_fields = cstr!(snippet_fields);
+    let snippet_fields = match serde_json::from_str::<Vec<String>>(&snippet_fields) {
+        Ok(v) => v,
+        Err(err) => {
+            update_last_error(err);
+            return 0;
+        }
+    };
+    let highlight_prefix = cstr!(highlight_prefix);
+    let highlight_postfix = cstr!(highlight_postfix);
+
+    let task = search::search_with_snippets(
+        query,
+        fields,
+        snippet_fields,
+        max_num_chars as usize,
+        highlight_prefix,
+        highlight_postfix,
+        page_start as usize,
+        page_size as usize,
+    );
+    let t = Isolate::new(port).task(task);
+    rt.spawn(t);
+    1
+}
+
+#[no_mangle]
+pub extern "C" fn se_search_fuzzy(
+    port: i64,
+    query: *const c_char,
+    fields: *const c_char,
+    max_distance: u32,
+    page_start: u32,
+    page_size: u32,
+) -> i32 {
+    let rt = runtime!();
+    let query = cstr!(query);
+    let fields = cstr!(fields);
+    let fields = match serde_json::from_str::<Vec<String>>(&fields) {
+        Ok(v) => v,
+        Err(err) => {
+            update_last_error(err);
+            return 0;
+        }
+    };
+
+    let task = search::search_fuzzy(
+        query,
+        fields,
+        max_distance,
+        page_start as usize,
+        page_size as usize,
+    );
+    let t = Isolate::new(port).task(task);
+    rt.spawn(t);
+    1
+}
+
+#[no_mangle]
+pub extern "C" fn se_search_proximity(
+    port: i64,
+    query: *const c_char,
+    fields: *const c_char,
+    proximity_weight: f32,
+    page_start: u32,
+    page_size: u32,
+) -> i32 {
+    let rt = runtime!();
+    let query = cstr!(query);
+    let fields = cstr!(fields);
+    let fields = match serde_json::from_str::<Vec<String>>(&fields) {
+        Ok(v) => v,
+        Err(err) => {
+            update_last_error(err);
+            return 0;
+        }
+    };
+
+    let task = search::search_proximity(
+        query,
+        fields,
+        proximity_weight,
+        page_start as usize,
+        page_size as usize,
+    );
+    let t = Isolate::new(port).task(task);
+    rt.spawn(t);
+    1
+}
+
+#[no_mangle]
+pub extern "C" fn se_search_derived(
+    port: i64,
+    query: *const c_char,
+    fields: *const c_char,
+    enable_derivation: i32,
+    page_start: u32,
+    page_size: u32,
+) -> i32 {
+    let rt = runtime!();
+    let query = cstr!(query);
+    let fields = cstr!(fields);
+    let fields = match serde_json::from_str::<Vec<String>>(&fields) {
+        Ok(v) => v,
+        Err(err) => {
+            update_last_error(err);
+            return 0;
+        }
+    };
+
+    let task = search::search_derived(
+        query,
+        fields,
+        enable_derivation != 0,
+        page_start as usize,
+        page_size as usize,
+    );
+    let t = Isolate::new(port).task(task);
+    rt.spawn(t);
+    1
+}
+
 #[no_mangle]
 pub extern "C" fn se_delete_by_str(port: i64, field: *const c_char, value: *const c_char) -> i32 {
     let rt = runtime!();